@@ -1,4 +1,5 @@
 import os
+import copy
 import json
 import re
 import shutil
@@ -22,6 +23,22 @@ USER = 'Y-RyuZU'
 REPO = 'MinecraftModsLocalizer'
 VERSION = 'v1.5.3'
 
+# ロケールを説明するキーは翻訳せず、日本語用の値を設定します。
+LOCALE_DESCRIPTORS = {
+    'language.name': '日本語',
+    'language.region': '日本',
+    'language.code': 'ja_jp',
+}
+
+# ひらがな・カタカナ・漢字のいずれかを含むかで日本語の文字列かを判定します。
+JAPANESE_PATTERN = re.compile('[\u3040-\u30FF\u3400-\u4DBF\u4E00-\u9FFF]')
+
+# en.json や ja.json のような2文字のロケール名を使っているModのための対応表
+LOCALE_ALIASES = {
+    'en_us': 'en',
+    'ja_jp': 'ja',
+}
+
 def get_latest_release_tag(user, repo):
     """
     GitHubのリリースから最新のタグ名を取得する関数
@@ -55,39 +72,149 @@ def extract_specific_file(zip_filepath, file_name, dest_dir):
     return False
 
 
+def skip_json_whitespace_and_comments(text, i):
+    """
+    i から始まる空白とコメントを読み飛ばし、次の文字の位置を返す関数
+    """
+    length = len(text)
+    while i < length:
+        if text[i].isspace():
+            i += 1
+        elif text.startswith('//', i):
+            end = text.find('\n', i)
+            i = length if end == -1 else end
+        elif text.startswith('/*', i):
+            end = text.find('*/', i + 2)
+            i = length if end == -1 else end + 2
+        else:
+            break
+    return i
+
+
+def strip_json_comments(text):
+    """
+    JSON5風の // 行コメント、/* */ ブロックコメント、末尾カンマを取り除く関数
+    文字列リテラル内の // や /* はそのまま残します。
+    """
+    result = []
+    i = 0
+    length = len(text)
+    in_string = False
+
+    while i < length:
+        c = text[i]
+        if in_string:
+            result.append(c)
+            if c == '\\' and i + 1 < length:
+                result.append(text[i + 1])
+                i += 1
+            elif c == '"':
+                in_string = False
+        elif c == '"':
+            in_string = True
+            result.append(c)
+        elif text.startswith('//', i):
+            end = text.find('\n', i)
+            i = length if end == -1 else end
+            continue
+        elif text.startswith('/*', i):
+            end = text.find('*/', i + 2)
+            i = length if end == -1 else end + 2
+            continue
+        elif c == ',':
+            # 閉じ括弧直前の末尾カンマは出力しません。
+            next_index = skip_json_whitespace_and_comments(text, i + 1)
+            if next_index >= length or text[next_index] not in '}]':
+                result.append(c)
+        else:
+            result.append(c)
+        i += 1
+
+    return ''.join(result)
+
+
+def load_json_lenient(text):
+    try:
+        return json.loads(text)
+    except json.JSONDecodeError:
+        # コメント付き(JSON5風)のlangファイルを考慮し、コメントを除いて再解析します。
+        return json.loads(strip_json_comments(text))
+
+
+def is_translatable_value(value):
+    """
+    数値、リソースID(namespace:path)、カラーコード(#RRGGBB)など翻訳してはいけない値を判定する関数
+    """
+    stripped = value.strip()
+    if not stripped:
+        return False
+    if re.fullmatch(r'[+-]?\d+(\.\d+)?', stripped):
+        return False
+    if re.fullmatch(r'[a-z0-9_.-]+:[a-z0-9_./-]+', stripped):
+        return False
+    if re.fullmatch(r'#[0-9A-Fa-f]{6}', stripped):
+        return False
+    return True
+
+
 def extract_map_from_json(file_path, collected_map):
     if os.path.exists(file_path):
         logging.info(f"Extract keys in en_us.json(or ja_jp.json) in {file_path}")
         try:
             with open(file_path, 'r', encoding="utf-8") as f:
-                content = json.load(f)
+                content = load_json_lenient(f.read())
+
+            preserved_count = 0
+            translated_count = 0
 
             # 値が英語でコメント以外のキーのみを保存します。
             for key, value in content.items():
-                if not key.startswith("_comment") and not re.search('[\u3040-\u30FF\u3400-\u4DBF\u4E00-\u9FFF]', value):
+                # 誤って数値や真偽値が入っているlangファイルでもファイルごと読み飛ばさず、その値だけ除外します。
+                if not isinstance(value, str):
+                    if isinstance(value, (dict, list)) or value is None:
+                        logging.warning(f"Skipping non-string value for {key} in {file_path}")
+                        continue
+                    # true/false などを翻訳すると日本語の単語になってしまうため、元の値(en_us)のまま残します。
+                    logging.warning(f"Preserving non-string value {json.dumps(value)} for {key} in {file_path}")
+                    preserved_count += 1
+                    continue
+
+                if not key.startswith("_comment") and not JAPANESE_PATTERN.search(value):
+                    # 数値やリソースIDは翻訳に回さず、元の値(en_us)のまま残します。
+                    if not is_translatable_value(value):
+                        preserved_count += 1
+                        continue
                     # 改行を削除(翻訳時扱いがめんどくさいため)
                     sanitized_value = value.replace('\n', '')
                     collected_map[key] = sanitized_value
-                if re.search('[\u3040-\u30FF\u3400-\u4DBF\u4E00-\u9FFF]', value):
+                if JAPANESE_PATTERN.search(value):
+                    # 同梱の翻訳で既に日本語になっているキーは翻訳対象から外します。
                     collected_map.pop(key, None)
-                    break
+                    translated_count += 1
+
+            if translated_count:
+                logging.info(f"{translated_count} of {len(content)} keys are already translated in {file_path}")
+            if preserved_count:
+                logging.info(f"Preserved {preserved_count} non-translatable values (numbers, resource ids, colors) in {file_path}")
 
         except json.JSONDecodeError:
             logging.info(
                 f"Failed to load or process JSON from {file_path}. Skipping this mod for translation. Please check the file for syntax errors.")
+        except UnicodeDecodeError:
+            logging.warning(
+                f"{file_path} is not encoded in UTF-8 (e.g. Shift-JIS). Skipping this file to avoid mojibake.")
     else:
         logging.info(f"Could not find {file_path}. Skipping this mod for translation.")
 
 
-def get_mod_name_from_jar(jar_path):
-    with zipfile.ZipFile(jar_path, 'r') as zip_ref:
-        asset_dirs_with_lang = set()
-        for name in zip_ref.namelist():
-            parts = name.split('/')
-            if len(parts) > 3 and parts[0] == 'assets' and parts[2] == 'lang' and parts[1] != 'minecraft':
-                asset_dirs_with_lang.add(parts[1])
-        if asset_dirs_with_lang:
-            return list(asset_dirs_with_lang)[0]
+def get_mod_name_from_jar(zip_ref):
+    asset_dirs_with_lang = set()
+    for name in zip_ref.namelist():
+        parts = name.replace('\\', '/').split('/')
+        if len(parts) > 3 and parts[0] == 'assets' and parts[2] == 'lang' and parts[1] != 'minecraft':
+            asset_dirs_with_lang.add(parts[1])
+    if asset_dirs_with_lang:
+        return list(asset_dirs_with_lang)[0]
     return None
 
 
@@ -147,91 +274,129 @@ def split_file(file_path, max_size=800000):  # max_size in bytes
     return parts
 
 
-def translate_batch_deepl(file_path, translated_map=None):
-    chunks = split_file(file_path)
+def assemble_translations(translated_map, translated_parts):
+    """
+    分割して翻訳した各パートの結果を元のキーに対応づけて1つのmapにまとめる関数
+    translated_parts は (翻訳前の行リスト, 翻訳後の行リスト) のリストです。
+    """
     result_keys = []
     result_values = []
+    for part_keys, part_values in translated_parts:
+        result_keys.extend(part_keys)
+        result_values.extend(part_values)
+
+    logging.info(f"result_keys: {len(result_keys)}, result_values: {len(result_values)}")
+
     result_map = {}
+    if len(result_keys) == len(result_values) and len(result_keys) == len(translated_map):
+        keys_list = list(translated_map.keys())
+        for idx, after in enumerate(result_values):
+            result_map[keys_list[idx]] = after
+    else:
+        logging.info("the number of keys and values does not match.")
+        # 行数が一致するパートのみ対応づけ、ずれた翻訳が混ざらないようにします。
+        translations = {}
+        for part_keys, part_values in translated_parts:
+            if len(part_keys) == len(part_values):
+                translations.update(zip(part_keys, part_values))
+            else:
+                logging.error(f"Part with {len(part_keys)} lines returned {len(part_values)} lines. Skipping this part.")
+        for key, value in translated_map.items():
+            if value in translations:
+                result_map[key] = translations[value]
+
+    missing_keys = [key for key in translated_map if key not in result_map]
+    if missing_keys:
+        logging.info(f"{len(missing_keys)} strings were not translated:")
+        for key in missing_keys:
+            logging.info(f"  {key}")
+
+    return result_map
+
+
+def upload_part_deepl(part, file_path):
+    """
+    分割したパートをDEEPLにアップロードし、(document_id, document_key)を返す関数
+    アップロードに失敗した場合は None を返します。
+    """
+    with open(part, 'rb') as f:
+        response = requests.post(
+            UPLOAD_URL,
+            headers={
+                'Authorization': f'DeepL-Auth-Key {API_KEY}'
+            },
+            data={
+                'source_lang': 'EN',
+                'target_lang': 'JA'
+            },
+            files={
+                'file': f
+            }
+        )
+
+    response_data = response.json()
+    document_id = response_data.get('document_id')
+    document_key = response_data.get('document_key')
+
+    if not document_id or not document_key:
+        logging.info(response.status_code)
+        logging.info(response.text)
+        logging.error(f"Failed to translate {part} in {file_path}.")
+        return None
+
+    return document_id, document_key
+
+
+def translate_part_deepl(part, file_path, pending_documents):
+    """
+    分割したパートを1つDEEPLで翻訳し、(翻訳前の行リスト, 翻訳後の行リスト)を返す関数
+    翻訳に失敗した場合は None を返します。
+    通信エラーや不正なレスポンスも None として扱い、再試行に回します。
+    アップロード済みの文書は pending_documents に残し、再試行では同じ文書の完了を待つことで二重課金を防ぎます。
+    """
+    try:
+        return request_part_deepl(part, file_path, pending_documents)
+    except (requests.RequestException, ValueError, KeyError) as e:
+        logging.error(f"Failed to translate {part} in {file_path}: {e!r}")
+        return None
+
+
+def request_part_deepl(part, file_path, pending_documents):
     timeout = 60 * 10
+    start_time = time.time()
 
-    with open(file_path, 'r', encoding='utf-8') as f:
+    part_keys = []
+
+    # Get original keys for this part
+    with open(part, 'r', encoding='utf-8') as f:
         content = f.read()
         char_count = len(content)
 
-        if translated_map is None:
-            translated_map = {line.rstrip('\n'): line.rstrip('\n') for line in content.splitlines()}
-
-        logging.info(f"The file {file_path} contains ({len(translated_map)} lines, {char_count} characters)...")
+        for line in content.splitlines():
+            part_keys.append(line.rstrip('\n'))
 
-    for part in chunks:
-        start_time = time.time()
-
-        part_keys = []
-
-        # Get original keys for this part
-        with open(part, 'r', encoding='utf-8') as f:
-            content = f.read()
-            char_count = len(content)
-
-            for line in content.splitlines():
-                part_keys.append(line.rstrip('\n'))
-
-            logging.info(f"The file {part} contains ({len(part_keys)} lines, {char_count} characters)...")
-
-        with open(part, 'rb') as f:
-            response = requests.post(
-                UPLOAD_URL,
-                headers={
-                    'Authorization': f'DeepL-Auth-Key {API_KEY}'
-                },
-                data={
-                    'source_lang': 'EN',
-                    'target_lang': 'JA'
-                },
-                files={
-                    'file': f
-                }
-            )
-
-        response_data = response.json()
-        document_id = response_data.get('document_id')
-        document_key = response_data.get('document_key')
-
-        if not document_id or not document_key:
-            logging.info(response.status_code)
-            logging.info(response.text)
-            logging.error(f"Failed to translate {part} in {file_path}. Skipping...")
-            continue
+        logging.info(f"The file {part} contains ({len(part_keys)} lines, {char_count} characters)...")
 
-        logging.info(f"Translating {part} in {file_path}...")
+    if part in pending_documents:
+        document_id, document_key = pending_documents[part]
+        logging.info(f"Resuming {part} in {file_path} without uploading it again (document_id: {document_id})...")
+    else:
+        document = upload_part_deepl(part, file_path)
+        if document is None:
+            return None
+        document_id, document_key = document
+        pending_documents[part] = document
 
-        while True:
-            elapsed_time = time.time() - start_time
-            if elapsed_time > timeout:
-                logging.error(f"Timeout reached while waiting for translation for {part} in {file_path}.")
-                break
+    logging.info(f"Translating {part} in {file_path}...")
 
-            status_response = requests.post(
-                CHECK_STATUS_URL_TEMPLATE.format(document_id),
-                headers={
-                    'Authorization': f'DeepL-Auth-Key {API_KEY}',
-                    'Content-Type': 'application/json'
-                },
-                json={
-                    'document_key': document_key
-                }
-            )
-            status_data = status_response.json()
-
-            if status_data['status'] == "done":
-                logging.info(f"Translation for {part} in {file_path} completed!")
-                break
-            else:
-                logging.info(status_data)
-                time.sleep(10)
+    while True:
+        elapsed_time = time.time() - start_time
+        if elapsed_time > timeout:
+            logging.error(f"Timeout reached while waiting for translation for {part} in {file_path} (document_id: {document_id}).")
+            return None
 
-        download_response = requests.post(
-            DOWNLOAD_URL_TEMPLATE.format(document_id),
+        status_response = requests.post(
+            CHECK_STATUS_URL_TEMPLATE.format(document_id),
             headers={
                 'Authorization': f'DeepL-Auth-Key {API_KEY}',
                 'Content-Type': 'application/json'
@@ -240,47 +405,100 @@ def translate_batch_deepl(file_path, translated_map=None):
                 'document_key': document_key
             }
         )
+        status_data = status_response.json()
 
-        # part_translated = f"translated_{part}"
-        # with open(part_translated, 'wb') as f:
-        #     f.write(download_response.content)
-        #
-        # with open(part_translated, 'r', encoding='utf-8') as f:
-        #     for line in f:
-        #         translated_parts_value.append(line.rstrip('\n'))
+        if status_data['status'] == "done":
+            logging.info(f"Translation for {part} in {file_path} completed!")
+            break
+        elif status_data['status'] == "error":
+            logging.error(f"DeepL reported an error for {part} in {file_path}: {status_data}")
+            # DEEPL側で失敗した文書は待っても完了しないため、再試行ではアップロードし直します。
+            del pending_documents[part]
+            return None
+        else:
+            logging.info(status_data)
+            time.sleep(10)
+
+    download_response = requests.post(
+        DOWNLOAD_URL_TEMPLATE.format(document_id),
+        headers={
+            'Authorization': f'DeepL-Auth-Key {API_KEY}',
+            'Content-Type': 'application/json'
+        },
+        json={
+            'document_key': document_key
+        }
+    )
 
-        buffer = BytesIO(download_response.content)
+    # ダウンロードに失敗した場合は、再試行で同じ文書をもう一度ダウンロードします。
+    if download_response.status_code != 200:
+        logging.error(f"Failed to download {part} in {file_path}: {download_response.status_code} {download_response.text}")
+        return None
 
-        part_values = []
+    # part_translated = f"translated_{part}"
+    # with open(part_translated, 'wb') as f:
+    #     f.write(download_response.content)
+    #
+    # with open(part_translated, 'r', encoding='utf-8') as f:
+    #     for line in f:
+    #         translated_parts_value.append(line.rstrip('\n'))
 
-        # バッファの内容を文字列として読み取る（エンコーディングを指定）
-        buffer.seek(0)  # バッファの先頭にカーソルを移動
-        text_data = buffer.read().decode('utf-8')
+    buffer = BytesIO(download_response.content)
 
-        # テキストデータを行ごとに処理
-        for line in text_data.splitlines():
-            part_values.append(line.rstrip('\n'))
+    part_values = []
 
-        # バッファを閉じる（メモリをクリーンアップ）
-        buffer.close()
+    # バッファの内容を文字列として読み取る（エンコーディングを指定）
+    buffer.seek(0)  # バッファの先頭にカーソルを移動
+    text_data = buffer.read().decode('utf-8')
 
-        result_keys.extend(part_keys)
-        result_values.extend(part_values)
+    # テキストデータを行ごとに処理
+    for line in text_data.splitlines():
+        part_values.append(line.rstrip('\n'))
 
-        logging.info(f"key: {len(part_keys)}, value: {len(part_values)}")
+    # バッファを閉じる（メモリをクリーンアップ）
+    buffer.close()
 
-    logging.info(f"result_keys: {len(result_keys)}, result_values: {len(result_values)}")
-    if len(result_keys) == len(result_values) and len(result_keys) == len(translated_map):
-        keys_list = list(translated_map.keys())
-        for idx, (before, after) in enumerate(zip(result_keys, result_values)):
-            key = keys_list[idx]
-            result_map[key] = after
-    else:
-        logging.info("the number of keys and values does not match.")
-        for key, value in translated_map.items():
-            for before, after in zip(result_keys, result_values):
-                if value == before:
-                    result_map[key] = after
+    logging.info(f"key: {len(part_keys)}, value: {len(part_values)}")
+
+    del pending_documents[part]
+    if len(part_keys) != len(part_values):
+        logging.error(f"The translation of {part} in {file_path} has {len(part_values)} lines instead of {len(part_keys)}.")
+        return None
+    return part_keys, part_values
+
+
+def translate_batch_deepl(file_path, translated_map=None):
+    chunks = split_file(file_path)
+
+    with open(file_path, 'r', encoding='utf-8') as f:
+        content = f.read()
+        char_count = len(content)
+
+        if translated_map is None:
+            translated_map = {line.rstrip('\n'): line.rstrip('\n') for line in content.splitlines()}
+
+        logging.info(f"The file {file_path} contains ({len(translated_map)} lines, {char_count} characters)...")
+
+    part_results = {}
+    failed_parts = []
+    # タイムアウトしたパートの文書はここに残り、再試行で同じ文書の完了を待ちます。
+    pending_documents = {}
+    for part in chunks:
+        part_results[part] = translate_part_deepl(part, file_path, pending_documents)
+        if part_results[part] is None:
+            failed_parts.append(part)
+
+    # 失敗したパートは最後にもう一度だけ翻訳を試みます。
+    if failed_parts:
+        logging.info(f"Retrying {len(failed_parts)} failed parts in {file_path}...")
+        for part in failed_parts:
+            part_results[part] = translate_part_deepl(part, file_path, pending_documents)
+            if part_results[part] is None:
+                logging.error(f"Failed to translate {part} in {file_path} after retrying. Skipping...")
+
+    translated_parts = [part_results[part] for part in chunks if part_results[part] is not None]
+
+    result_map = assemble_translations(translated_map, translated_parts)
 
     # Remove the temporary file
     for part in chunks:
@@ -292,40 +510,237 @@ def translate_batch_deepl(file_path, translated_map=None):
     return result_map
 
 
-def process_jar_file(log_directory, jar_path, collected_map):
-    mod_name = get_mod_name_from_jar(jar_path)
-    if mod_name is None:
-        logging.info(f"Could not determine mod name for {jar_path}")
-        return
+def translate_map_deepl(collected_map):
+    """
+    同じ英文が複数のキーで使われている場合でも1度だけDEEPLに送り、翻訳結果を各キーへ展開する関数
+    """
+    unique_values = list(dict.fromkeys(collected_map.values()))
+    logging.info(f"{len(collected_map)} keys share {len(unique_values)} unique strings.")
 
-    lang_path_in_jar = Path(f'assets/{mod_name}/lang/')
-    ja_jp_path_in_jar = os.path.join(lang_path_in_jar, 'ja_jp.json')
-    en_us_path_in_jar = os.path.join(lang_path_in_jar, 'en_us.json')
-    ja_jp_path_in_jar_str = str(ja_jp_path_in_jar).replace('\\', '/')
-    en_us_path_in_jar_str = str(en_us_path_in_jar).replace('\\', '/')
+    # ファイルに書き込み
+    with open('tmp.txt', 'w', encoding='utf-8') as f:
+        for value in unique_values:
+            f.write(value + '\n')
+
+    unique_translations = translate_batch_deepl('tmp.txt', {value: value for value in unique_values})
 
-    logging.info(f"Extract en_us.json or ja_jp.json in {jar_path / lang_path_in_jar}")
+    return {key: unique_translations[value] for key, value in collected_map.items() if value in unique_translations}
+
+
+def iter_text_components(component):
+    """
+    JSONテキストコンポーネント内の 'text' を持つ要素を 'extra' も含めて順に返す関数
+    """
+    if isinstance(component, list):
+        for child in component:
+            yield from iter_text_components(child)
+    elif isinstance(component, dict):
+        if isinstance(component.get('text'), str) and component['text']:
+            yield component
+        yield from iter_text_components(component.get('extra', []))
+
+
+def parse_text_component(value):
+    if not value.lstrip().startswith(('{', '[')):
+        return None
+    try:
+        component = json.loads(value)
+    except json.JSONDecodeError:
+        return None
+    if not any(True for _ in iter_text_components(component)):
+        return None
+    return component
+
+
+def extract_text_components(collected_map):
+    """
+    値がJSONテキストコンポーネント({"text": ...})のキーについて、'text' の部分だけを翻訳対象として取り出す関数
+    JSONごと翻訳すると構造が壊れるため、元のコンポーネントは別に保持します。
+    """
+    components = {}
+    for key, value in list(collected_map.items()):
+        component = parse_text_component(value)
+        if component is None:
+            continue
+
+        del collected_map[key]
+        components[key] = component
+        for idx, part in enumerate(iter_text_components(component)):
+            # tmp.txt は1行1エントリなので、改行は取り除く
+            collected_map[f'{key}#text{idx}'] = part['text'].replace('\n', '')
+
+    if components:
+        logging.info(f"Found {len(components)} JSON text component values. Translating only their text fields.")
+    return components
+
+
+def restore_text_components(translated_map, components):
+    for key, component in components.items():
+        for idx, part in enumerate(iter_text_components(component)):
+            part['text'] = translated_map.pop(f'{key}#text{idx}', part['text'])
+        translated_map[key] = json.dumps(component, ensure_ascii=False)
+
+
+def find_placeholder_mismatches(source_map, translated_map):
+    """
+    翻訳前後で書式指定子(%s, %d, %1$s など)が変わってしまったキーを返す関数
+    """
+    pattern = re.compile(r'%(?:\d+\$)?[dscf]')
+    mismatches = []
+    for key, translated in translated_map.items():
+        if key not in source_map:
+            continue
+        if sorted(pattern.findall(source_map[key])) != sorted(pattern.findall(translated)):
+            mismatches.append(key)
+    return mismatches
+
+
+def measure_untranslated_ratio(source_map, translated_map):
+    """
+    翻訳後も元の英文と同じままになっているキーの割合を返す関数
+    """
+    if not translated_map:
+        return 0.0
+    unchanged = sum(1 for key, value in translated_map.items() if source_map.get(key) == value)
+    return unchanged / len(translated_map)
+
+
+def detect_output_language_mismatch(translated_map):
+    """
+    翻訳結果に日本語(ひらがな・カタカナ・漢字)が含まれず、英単語が2語以上残っているキーを返す関数
+    固有名詞だけの短い値を誤検出しないよう、判定は控えめにしています。
+    """
+    mismatches = []
+    for key, value in translated_map.items():
+        # テキストコンポーネントは 'text' の部分だけを判定します。
+        component = parse_text_component(value)
+        if component is not None:
+            value = ''.join(part['text'] for part in iter_text_components(component))
+        if JAPANESE_PATTERN.search(value):
+            continue
+        if len(re.findall(r'[A-Za-z]{2,}', value)) >= 2:
+            mismatches.append(key)
+    return mismatches
+
+
+def export_review(source_map, translated_map, review_path):
+    """
+    翻訳前後の文字列をキーごとに並べたレビュー用のJSONを書き出す関数
+    翻訳されなかったキーは target が null になります。
+    """
+    review = {key: {'source': source, 'target': translated_map.get(key)} for key, source in sorted(source_map.items())}
+
+    with open(review_path, 'w', encoding='utf-8') as f:
+        json.dump(review, f, ensure_ascii=False, indent=4)
+    logging.info(f"Wrote review file to {review_path}")
+
+
+def validate_jar_path(jar_path):
+    """
+    jarファイルが読み込めるか確認し、問題がある場合はその理由を返す関数
+    """
+    if not os.path.exists(jar_path):
+        return "file does not exist"
+    if not os.path.isfile(jar_path):
+        return "not a file"
+    if not zipfile.is_zipfile(jar_path):
+        return "not a valid zip archive"
+    return None
+
+
+def find_lang_entries(zip_ref, lang_directory, locale):
+    """
+    lang_directory 直下にある locale で始まるjsonファイル(en_us.json, en_us_extra.json など)を返す関数
+    LOCALE_ALIASES にある2文字のロケール名のファイル(en.json など)も対象にします。
+    区切り文字に '\\' を使っているjarも考慮し、(エントリ名, '/' 区切りのパス) のリストを名前順で返します。
+    """
+    alias = LOCALE_ALIASES.get(locale)
+    entries = []
+    for name in zip_ref.namelist():
+        normalized_name = name.replace('\\', '/')
+        if not normalized_name.startswith(lang_directory):
+            continue
+        file_name = normalized_name[len(lang_directory):]
+        if '/' in file_name or not file_name.endswith('.json'):
+            continue
+        if file_name.startswith(locale) or (alias and file_name == f'{alias}.json'):
+            entries.append((name, normalized_name))
+    return sorted(entries, key=lambda entry: entry[1])
+
+
+def process_jar_file(log_directory, jar_path, collected_map):
+    extracted_paths = []
+
+    # jarは1度だけ開き、Mod名の判定とlangファイルの取り出しを続けて行います。
     with zipfile.ZipFile(jar_path, 'r') as zip_ref:
-        if en_us_path_in_jar_str in zip_ref.namelist():
-            extract_specific_file(jar_path, en_us_path_in_jar_str, log_directory)
-        if ja_jp_path_in_jar_str in zip_ref.namelist():
-            extract_specific_file(jar_path, ja_jp_path_in_jar_str, log_directory)
+        mod_name = get_mod_name_from_jar(zip_ref)
+        if mod_name is None:
+            logging.info(f"Could not determine mod name for {jar_path}")
+            return
 
-    en_us_path = os.path.join(log_directory, en_us_path_in_jar)
-    ja_jp_path = os.path.join(log_directory, ja_jp_path_in_jar)
-    extract_map_from_json(en_us_path, collected_map)
-    extract_map_from_json(ja_jp_path, collected_map)
+        lang_path_in_jar_str = f'assets/{mod_name}/lang/'
 
+        logging.info(f"Extract en_us.json or ja_jp.json in {jar_path / Path(lang_path_in_jar_str)}")
+        # en_us.json と en_us_extra.json のように分割されている場合もまとめて読み込みます。
+        for locale in ('en_us', 'ja_jp'):
+            for entry_name, path_in_jar_str in find_lang_entries(zip_ref, lang_path_in_jar_str, locale):
+                extracted_path = os.path.join(log_directory, path_in_jar_str)
+                os.makedirs(os.path.dirname(extracted_path), exist_ok=True)
+                with open(extracted_path, 'wb') as f:
+                    f.write(zip_ref.read(entry_name))
+                extracted_paths.append(extracted_path)
 
-def translate_from_jar(log_directory):
-    if not os.path.exists(RESOURCE_DIR):
-        os.makedirs(os.path.join(RESOURCE_DIR, 'assets', 'japanese', 'lang'))
+    if not extracted_paths:
+        logging.info(f"Could not find en_us.json or ja_jp.json in {jar_path}. Skipping this mod for translation.")
 
-    collected_map = {}
+    for extracted_path in extracted_paths:
+        extract_map_from_json(extracted_path, collected_map)
+
+
+class ExistingPackError(Exception):
+    """
+    更新モードで既存パックのja_jp.jsonが読み込めない場合の例外
+    ユーザーの翻訳を失わないよう、上書きせずに翻訳を中止します。
+    """
+
+
+def translate_from_jar(log_directory, update_existing=False, include_zip=False):
+    lang_path = os.path.join(RESOURCE_DIR, 'assets', 'japanese', 'lang', 'ja_jp.json')
+    os.makedirs(os.path.dirname(lang_path), exist_ok=True)
+
+    # 更新モードでは既存パックの翻訳を読み込み、新しい翻訳をそこへ追加します。
+    existing_map = {}
+    if update_existing and os.path.exists(lang_path):
+        try:
+            with open(lang_path, 'r', encoding='utf-8') as f:
+                existing_map = load_json_lenient(f.read())
+        except (json.JSONDecodeError, UnicodeDecodeError) as e:
+            logging.error(f"Could not read the existing pack {lang_path}: {e}")
+            raise ExistingPackError(
+                f"Could not read {lang_path}.\nPlease fix the file or uncheck 'Update Existing Pack'.")
+        logging.info(f"Updating existing resource pack {RESOURCE_DIR} ({len(existing_map)} translations)")
 
-    extracted_pack_mcmeta = False
+    collected_map = {}
+    failed_jars = []
+
+    # 更新モードで既にpack.mcmetaがある場合はそのまま残します。
+    extracted_pack_mcmeta = update_existing and os.path.exists(os.path.join(RESOURCE_DIR, 'pack.mcmeta'))
+    # .zip で配布されているModは誤検出を避けるため、指定された場合のみ対象にします。
+    mod_extensions = ('.jar', '.zip') if include_zip else ('.jar',)
+    # 翻訳を始める前に読み込めないjarを除外します。
+    jar_files = []
     for filename in os.listdir(MODS_DIR):
-        if filename.endswith('.jar'):
+        if filename.endswith(mod_extensions):
+            jar_error = validate_jar_path(os.path.join(MODS_DIR, filename))
+            if jar_error:
+                logging.error(f"Skipping {filename}: {jar_error}")
+                failed_jars.append((filename, jar_error))
+            else:
+                jar_files.append(filename)
+
+    for filename in jar_files:
+        # 壊れたjarが1つあっても他のModの翻訳は続行します。
+        try:
             # Extract pack.mcmeta if it exists in the jar
             if not extracted_pack_mcmeta:
                 extracted_pack_mcmeta = extract_specific_file(os.path.join(MODS_DIR, filename), 'pack.mcmeta',
@@ -333,6 +748,28 @@ def translate_from_jar(log_directory):
                 update_description(os.path.join(RESOURCE_DIR, 'pack.mcmeta'), '日本語化パック')
 
             process_jar_file(log_directory, os.path.join(MODS_DIR, filename), collected_map)
+        except Exception as e:
+            logging.error(f"Failed to process {filename}: {e}. Skipping this mod for translation.")
+            failed_jars.append((filename, str(e)))
+
+    if failed_jars:
+        logging.info(f"{len(failed_jars)} mod(s) could not be processed:")
+        for filename, error in failed_jars:
+            logging.info(f"  {filename}: {error}")
+
+    # 更新モードでは既存パックで翻訳済みのキーを再翻訳しません。
+    if existing_map:
+        already_translated = [key for key in collected_map if key in existing_map]
+        for key in already_translated:
+            del collected_map[key]
+        logging.info(f"Skipping {len(already_translated)} keys already in the existing pack. {len(collected_map)} new keys to translate.")
+
+    locale_map = {key: LOCALE_DESCRIPTORS[key] for key in LOCALE_DESCRIPTORS if collected_map.pop(key, None) is not None}
+
+    components = extract_text_components(collected_map)
+    source_map = dict(collected_map)
+    # 確認やレビューを元のキーで行えるよう、翻訳前のコンポーネントも保持します。
+    source_components = copy.deepcopy(components)
 
     # 変数代入部分が消されないようDEEPL翻訳に送る前にクオートで囲みます。
     pattern = re.compile(r'%[dscf]')
@@ -340,12 +777,7 @@ def translate_from_jar(log_directory):
         quoted_value = pattern.sub(lambda match: f'\'{match.group()}\'', value)
         collected_map[key] = quoted_value
 
-    # ファイルに書き込み
-    with open('tmp.txt', 'w', encoding='utf-8') as f:
-        for value in collected_map.values():
-            f.write(value + '\n')
-
-    translated_map = translate_batch_deepl('tmp.txt', collected_map)
+    translated_map = translate_map_deepl(collected_map)
 
     # クオートで囲まれた書式指定子を見つけ、クオートを取り除きます。
     pattern = re.compile(r"['\"](%[dscf])['\"]")
@@ -353,32 +785,139 @@ def translate_from_jar(log_directory):
         unquoted_value = pattern.sub(lambda match: match.group(1), value)
         translated_map[key] = unquoted_value
 
-    with open(os.path.join(RESOURCE_DIR, 'assets', 'japanese', 'lang', 'ja_jp.json'), 'w', encoding="utf-8") as f:
-        json.dump(dict(sorted(translated_map.items())), f, ensure_ascii=False, indent=4)
+    restore_text_components(source_map, source_components)
+    restore_text_components(translated_map, components)
+
+    placeholder_mismatches = find_placeholder_mismatches(source_map, translated_map)
+    if placeholder_mismatches:
+        logging.warning(f"{len(placeholder_mismatches)} translations changed their format specifiers. Please check them in-game:")
+        for key in placeholder_mismatches:
+            logging.warning(f"  {key}: {source_map[key]} -> {translated_map[key]}")
+
+    untranslated_ratio = measure_untranslated_ratio(source_map, translated_map)
+    logging.info(f"{untranslated_ratio:.1%} of translated strings are identical to the source.")
+    if untranslated_ratio > 0.5:
+        logging.warning("More than half of the strings came back untranslated. The translation may have failed.")
 
+    language_mismatches = detect_output_language_mismatch(translated_map)
+    if language_mismatches:
+        logging.warning(f"{len(language_mismatches)} translations do not look like Japanese:")
+        for key in language_mismatches:
+            logging.warning(f"  {key}: {translated_map[key]}")
 
-def translate_quests_from_json(file_path):
+    export_review(source_map, translated_map, os.path.join(log_directory, 'review.json'))
+
+    translated_map.update(locale_map)
+
+    existing_map.update(translated_map)
+
+    with open(lang_path, 'w', encoding="utf-8") as f:
+        json.dump(dict(sorted(existing_map.items())), f, ensure_ascii=False, indent=4)
+
+
+def translate_quests_from_json(file_path, backup_directory):
     collected_map = {}
 
     clean_json_file(file_path)
     extract_map_from_json(file_path, collected_map)
 
-    # Write the extracted strings to tmp.txt
-    with open('tmp.txt', 'w', encoding='utf-8') as f:
-        for value in collected_map.values():
-            f.write(value + '\n')
+    translated_map = translate_map_deepl(collected_map)
+
+    write_kubejs_lang(QUESTS_DIR1, translated_map, backup_directory)
+    write_kubejs_lang(QUESTS_DIR2, translated_map, backup_directory)
+
+
+def write_kubejs_lang(lang_directory, translated_map, backup_directory):
+    """
+    KubeJSのlangディレクトリにja_jp.jsonを書き込む関数
+    既存のja_jp.jsonがある場合は、バックアップを取ってからその内容に翻訳結果を追加します。
+    """
+    os.makedirs(lang_directory, exist_ok=True)
+    ja_jp_path = os.path.join(lang_directory, 'ja_jp.json')
+
+    merged_map = {}
+    if os.path.exists(ja_jp_path):
+        # kubejs と ftbquests の ja_jp.json が同じ名前にならないよう、親ディレクトリ名を付けて保存します。
+        backup_file = backup_directory / f'{Path(lang_directory).parent.name}_ja_jp.json'
+        shutil.copy(ja_jp_path, backup_file)
+        try:
+            with open(ja_jp_path, 'r', encoding='utf-8') as f:
+                merged_map = load_json_lenient(f.read())
+        except (json.JSONDecodeError, UnicodeDecodeError) as e:
+            logging.error(f"Could not read existing {ja_jp_path}: {e}. Overwriting it. The old file was backed up to {backup_file}")
+    merged_map.update(translated_map)
+
+    with open(ja_jp_path, 'w', encoding="utf-8") as f:
+        json.dump(dict(sorted(merged_map.items())), f, ensure_ascii=False, indent=4)
+    logging.info(f"Wrote {len(merged_map)} translations to {ja_jp_path}")
 
-    translated_map = translate_batch_deepl('tmp.txt', collected_map)
 
-    with open(os.path.join(QUESTS_DIR1 / 'ja_jp.json'), 'w', encoding="utf-8") as f:
-        json.dump(dict(sorted(translated_map.items())), f, ensure_ascii=False, indent=4)
-    with open(os.path.join(QUESTS_DIR2 / 'ja_jp.json'), 'w', encoding="utf-8") as f:
-        json.dump(dict(sorted(translated_map.items())), f, ensure_ascii=False, indent=4)
+def normalize_snbt_content(content):
+    """
+    SNBTの先頭のBOMと末尾のNULL文字などの制御文字を取り除く関数
+    """
+    if content.startswith('\ufeff'):
+        content = content[1:]
+    return re.sub(r'[\x00-\x08\x0b\x0c\x0e-\x1f]+$', '', content)
+
+
+def escape_snbt_string(value):
+    """
+    翻訳結果をSNBTの文字列リテラルに埋め込めるよう、" と \\ をエスケープする関数
+    元の文字列にあった \\n や \\" のようなエスケープはそのまま残します。
+    """
+    escaped = []
+    i = 0
+    while i < len(value):
+        c = value[i]
+        if c == '\\' and i + 1 < len(value) and value[i + 1] in '\\"\'nt':
+            escaped.append(value[i:i + 2])
+            i += 2
+            continue
+        if c in '\\"':
+            escaped.append('\\')
+        escaped.append(c)
+        i += 1
+    return ''.join(escaped)
+
+
+def get_snbt_structure(content):
+    """
+    文字列リテラルの外にある括弧と、文字列リテラルの位置(s)を順に取り出す関数
+    翻訳結果の " が閉じられずに残ったり、文字列が分割・結合されたりすると、この並びが元ファイルと一致しなくなります。
+    """
+    structure = []
+    in_string = False
+    escaped = False
+    for c in content:
+        if in_string:
+            if escaped:
+                escaped = False
+            elif c == '\\':
+                escaped = True
+            elif c == '"':
+                in_string = False
+                structure.append('s')
+        elif c == '"':
+            in_string = True
+        elif c in '{}[]':
+            structure.append(c)
+
+    if in_string:
+        structure.append('"')
+    return ''.join(structure)
+
+
+def validate_snbt(original, written):
+    """
+    翻訳後のSNBTが元のSNBTと同じ構造を保っているか確認する関数
+    """
+    return get_snbt_structure(original) == get_snbt_structure(written)
 
 
 def translate_quests_from_snbt(file_path):
     with open(file_path, 'r', encoding='utf-8') as f:
-        content = f.read()
+        content = normalize_snbt_content(f.read())
     logging.info(f"Translating {file_path}...")
 
     extracted_strings = []
@@ -398,6 +937,13 @@ def translate_quests_from_snbt(file_path):
         if inner_match:  # Non-empty strings
             extracted_strings.append(inner_match)
 
+    # {quest.title} のような翻訳キー参照は本文ではないため翻訳しません。
+    lang_key_pattern = re.compile(r'\{[\w.:-]+\}')
+    lang_key_count = sum(1 for s in extracted_strings if lang_key_pattern.fullmatch(s))
+    if lang_key_count:
+        logging.info(f"Skipping {lang_key_count} lang key references in {file_path}")
+        extracted_strings = [s for s in extracted_strings if not lang_key_pattern.fullmatch(s)]
+
     if len(extracted_strings) == 0:
         logging.info("No strings found. Skipping...")
         return
@@ -411,8 +957,14 @@ def translate_quests_from_snbt(file_path):
     translated_map = translate_batch_deepl('tmp.txt')
 
     # Substitute back the translated content
+    original_content = content
     for original, translated in translated_map.items():
-        content = content.replace(f'"{original}"', f'"{translated}"', 1)
+        content = content.replace(f'"{original}"', f'"{escape_snbt_string(translated)}"', 1)
+
+    # 構造が壊れた場合は元のファイルを残します。
+    if not validate_snbt(original_content, content):
+        logging.error(f"Translated {file_path} is no longer valid SNBT. Keeping the original file.")
+        return
 
     # Save the content back
     with open(file_path, 'w', encoding='utf-8') as f:
@@ -430,7 +982,7 @@ def translate_quests(log_directory):
     if os.path.exists(json_path):
         logging.info(f"en_us.json found in {QUESTS_DIR1}, translating from json...")
         shutil.copy(json_path, backup_directory)
-        translate_quests_from_json(json_path)
+        translate_quests_from_json(json_path, backup_directory)
     else:
         logging.info(f"en_us.json not found in {QUESTS_DIR1}, translating snbt files in directory...")
         nbt_files = list(QUESTS_DIR3.glob('*.snbt'))
@@ -438,7 +990,12 @@ def translate_quests(log_directory):
         for file in nbt_files:
             backup_file = backup_directory / file.name
             shutil.copy(file, backup_file)
-            translate_quests_from_snbt(file)
+            try:
+                translate_quests_from_snbt(file)
+            except Exception as e:
+                # 途中で失敗したファイルはバックアップから元に戻し、次のファイルへ進みます。
+                logging.error(f"Failed to translate {file}: {e}. Restoring from {backup_file}")
+                shutil.copy(backup_file, file)
 
     logging.info("Traslate snbt files Done!")
 
@@ -454,9 +1011,13 @@ def update_description(file_path, new_description):
         except json.JSONDecodeError as e:
             return
 
-    # 'description'の'text'を新しい値に更新
+    # 'description'(文字列 or テキストコンポーネントの'text')を新しい値に更新
     try:
-        if 'pack' in data and 'description' in data['pack'] and 'text' in data['pack']['description']:
+        if 'pack' not in data or 'description' not in data['pack']:
+            return
+        if isinstance(data['pack']['description'], str):
+            data['pack']['description'] = new_description
+        elif 'text' in data['pack']['description']:
             data['pack']['description']['text'] = new_description
         else:
             return
@@ -471,6 +1032,28 @@ def update_description(file_path, new_description):
             return
 
 
+def check_write_permissions(paths):
+    """
+    書き込み先のディレクトリに一時ファイルを作成・削除し、書き込めないパスとエラー内容を返す関数
+    ディレクトリがまだ存在しない場合は、存在する最も近い親ディレクトリで確認します。
+    """
+    errors = []
+    for path in paths:
+        directory = Path(path)
+        while not directory.exists() and directory != directory.parent:
+            directory = directory.parent
+
+        probe_path = directory / '.localizer_write_test'
+        try:
+            with open(probe_path, 'w', encoding='utf-8') as f:
+                f.write('')
+            os.remove(probe_path)
+        except OSError as e:
+            errors.append((str(path), str(e)))
+
+    return errors
+
+
 def setup_logging(directory):
     log_file = "translate.log"
 
@@ -503,6 +1086,8 @@ if __name__ == '__main__':
         [sg.Text("DEEPL API KEY")],
         [sg.InputText(key='DEEPL_API_KEY')],
         [sg.Checkbox('Use Free API', default=True, key='free')],
+        [sg.Checkbox('Update Existing Pack', default=False, key='update')],
+        [sg.Checkbox('Include .zip Mods', default=False, key='zip')],
         [sg.Button("Translate", key='translate')]
     ]
 
@@ -536,6 +1121,8 @@ if __name__ == '__main__':
             target = values['target']
             API_KEY = values['DEEPL_API_KEY']
             suffix = "-free" if values['free'] else ""
+            update_existing = values['update']
+            include_zip = values['zip']
 
             DEEPL_API_URL = f'https://api{suffix}.deepl.com/v2/translate'
             UPLOAD_URL = f"https://api{suffix}.deepl.com/v2/document"
@@ -547,14 +1134,31 @@ if __name__ == '__main__':
                 sg.popup('Please update the localizer.')
                 break
 
+            # 翻訳の途中で失敗しないよう、事前に書き込み先を確認します。
+            write_targets = [Path('.')]
+            if target in (select_options[0], select_options[2]):
+                write_targets.append(RESOURCE_DIR)
+            if target in (select_options[1], select_options[2]):
+                write_targets.extend([QUESTS_DIR1, QUESTS_DIR2, QUESTS_DIR3])
+
+            permission_errors = check_write_permissions(write_targets)
+            if permission_errors:
+                for path, error in permission_errors:
+                    logging.error(f"Cannot write to {path}: {error}")
+                sg.popup('Cannot write to:\n' + '\n'.join(path for path, _ in permission_errors))
+                break
+
             try:
                 if target == select_options[0]:
-                    translate_from_jar(log_directory)
+                    translate_from_jar(log_directory, update_existing, include_zip)
                 elif target == select_options[1]:
                     translate_quests(log_directory)
                 elif target == select_options[2]:
-                    translate_from_jar(log_directory)
+                    translate_from_jar(log_directory, update_existing, include_zip)
                     translate_quests(log_directory)
+            except ExistingPackError as e:
+                sg.popup(f'Translate Failed!\n{e}')
+                break
             except Exception as e:
                 logging.error(e)
                 sg.popup('Translate Failed!')